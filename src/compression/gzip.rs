@@ -0,0 +1,48 @@
+extern crate flate2;
+
+use std::io::{Read, Write};
+
+use self::flate2::Compression as Flate2Compression;
+use self::flate2::bufread::GzDecoder;
+use self::flate2::write::GzEncoder;
+
+use error::{Error, Result};
+use super::{Compressor, Decompressor};
+
+/// Compresses/decompresses message-set payloads using gzip, as used by
+/// most non-Java Kafka producers and consumers.
+pub struct Gzip;
+
+impl Compressor for Gzip {
+    fn compress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Flate2Compression::default());
+        try!(encoder.write_all(src).map_err(Error::Io));
+        encoder.finish().map_err(Error::Io)
+    }
+}
+
+impl Decompressor for Gzip {
+    fn reader<'a>(&self, src: &'a [u8]) -> Result<Box<Read + 'a>> {
+        Ok(Box::new(GzDecoder::new(src)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compressor, Decompressor, Gzip};
+
+    #[test]
+    fn test_roundtrip() {
+        let msg = b"This is test data for the gzip codec";
+        let compressed = Gzip.compress(msg).unwrap();
+        let decompressed = Gzip.decompress(&compressed).unwrap();
+        assert_eq!(&decompressed[..], &msg[..]);
+    }
+
+    #[test]
+    fn test_decompress_corrupt_input() {
+        // ~ a handful of bytes that aren't a valid gzip member at all
+        let garbage = [0u8, 1, 2, 3, 4, 5, 6, 7];
+        assert!(Gzip.decompress(&garbage).is_err());
+    }
+}
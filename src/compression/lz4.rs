@@ -0,0 +1,47 @@
+extern crate lz4_flex;
+
+use std::io::{Read, Write};
+
+use self::lz4_flex::frame::{FrameDecoder, FrameEncoder};
+
+use error::{Error, Result};
+use super::{Compressor, Decompressor};
+
+/// Compresses/decompresses message-set payloads using the LZ4 frame
+/// format, as negotiated by newer Kafka clients.
+pub struct Lz4;
+
+impl Compressor for Lz4 {
+    fn compress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = FrameEncoder::new(Vec::new());
+        try!(encoder.write_all(src).map_err(Error::Io));
+        encoder.finish().map_err(Error::Io)
+    }
+}
+
+impl Decompressor for Lz4 {
+    fn reader<'a>(&self, src: &'a [u8]) -> Result<Box<Read + 'a>> {
+        Ok(Box::new(FrameDecoder::new(src)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compressor, Decompressor, Lz4};
+
+    #[test]
+    fn test_roundtrip() {
+        let msg = b"This is test data for the lz4 codec";
+        let compressed = Lz4.compress(msg).unwrap();
+        let decompressed = Lz4.decompress(&compressed).unwrap();
+        assert_eq!(&decompressed[..], &msg[..]);
+    }
+
+    #[test]
+    fn test_decompress_corrupt_input() {
+        // ~ a truncated frame: a valid-looking magic number with none
+        // of the frame descriptor or data that must follow it
+        let truncated = [0x04, 0x22, 0x4d, 0x18];
+        assert!(Lz4.decompress(&truncated).is_err());
+    }
+}
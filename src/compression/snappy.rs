@@ -1,8 +1,10 @@
 extern crate rsnappy;
 
-use std::io::{self, Cursor, Read};
-use byteorder::{BigEndian, ByteOrder};
+use std::io::{self, BufRead, Cursor, Read, Write};
+use std::mem;
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 
+use crc::crc32c;
 use error::{Result, Error};
 
 // ~ Uncompresses 'src' into 'dst'.
@@ -67,10 +69,45 @@ fn test_validate_stream() {
     assert_eq!(rest, &[0x56]);
 }
 
+// ~ the stream-identifier chunk that opens every official-format
+// stream: type 0xff, a 3-byte little-endian length of 6, payload "sNaPpY"
+const STREAM_ID_CHUNK_TYPE: u8 = 0xff;
+const STREAM_ID_PAYLOAD: &'static [u8] = b"sNaPpY";
+
+/// Which of the two snappy stream formats a `SnappyReader` is parsing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// the chunked format produced by org.xerial.snappy.SnappyOutputStream:
+    /// just a big-endian `i32` compressed-length prefix per chunk.
+    Xerial,
+    /// the official snappy framing format
+    /// (https://github.com/google/snappy/blob/master/framing_format.txt),
+    /// with a stream-identifier chunk followed by CRC32C-checked chunks.
+    Official,
+}
+
+/// Sniffs the header of `stream` to determine which framing it uses and
+/// returns the remainder of the stream following the consumed header.
+fn detect_framing(stream: &[u8]) -> Result<(Framing, &[u8])> {
+    if stream.len() >= MAGIC.len() && &stream[..MAGIC.len()] == MAGIC {
+        Ok((Framing::Xerial, try!(validate_stream(stream))))
+    } else if stream.len() >= 10 && stream[0] == STREAM_ID_CHUNK_TYPE &&
+              &stream[1..4] == &[6, 0, 0] && &stream[4..10] == STREAM_ID_PAYLOAD {
+        Ok((Framing::Official, &stream[10..]))
+    } else {
+        Err(Error::InvalidInputSnappy)
+    }
+}
+
 // ~ An implementation of a reader over a stream of snappy compressed
-// chunks as produced by org.xerial.snappy.SnappyOutputStream
-// (https://github.com/xerial/snappy-java/ version: 1.1.1.*)
+// chunks, understanding both the chunked format produced by
+// org.xerial.snappy.SnappyOutputStream
+// (https://github.com/xerial/snappy-java/ version: 1.1.1.*) and the
+// official snappy frame format used by the broader snappy ecosystem.
 pub struct SnappyReader<'a> {
+    // which of the two stream formats we're parsing
+    framing: Framing,
+
     // the compressed data itself
     compressed_data: &'a [u8],
 
@@ -82,10 +119,11 @@ pub struct SnappyReader<'a> {
 }
 
 impl<'a> SnappyReader<'a> {
-    pub fn new(mut stream: &[u8]) -> Result<SnappyReader> {
-        stream = try!(validate_stream(stream));
+    pub fn new(stream: &[u8]) -> Result<SnappyReader> {
+        let (framing, rest) = try!(detect_framing(stream));
         Ok(SnappyReader {
-            compressed_data: stream,
+            framing: framing,
+            compressed_data: rest,
             uncompressed_pos: 0,
             uncompressed_chunk: Vec::new(),
         })
@@ -108,6 +146,13 @@ impl<'a> SnappyReader<'a> {
     }
 
     fn next_chunk(&mut self) -> Result<bool> {
+        match self.framing {
+            Framing::Xerial => self.next_chunk_xerial(),
+            Framing::Official => self.next_chunk_official(),
+        }
+    }
+
+    fn next_chunk_xerial(&mut self) -> Result<bool> {
         if self.compressed_data.is_empty() {
             return Ok(false);
         }
@@ -123,6 +168,56 @@ impl<'a> SnappyReader<'a> {
         Ok(true)
     }
 
+    // ~ reads chunks of the official frame format until it finds one
+    // carrying data (compressed or uncompressed), skipping over
+    // skippable/identifier chunks and erroring on reserved ones;
+    // validates the per-chunk CRC32C of the uncompressed payload
+    fn next_chunk_official(&mut self) -> Result<bool> {
+        loop {
+            if self.compressed_data.is_empty() {
+                return Ok(false);
+            }
+            if self.compressed_data.len() < 4 {
+                return Err(Error::UnexpectedEOF);
+            }
+            let chunk_type = self.compressed_data[0];
+            let length = (self.compressed_data[1] as usize) |
+                         ((self.compressed_data[2] as usize) << 8) |
+                         ((self.compressed_data[3] as usize) << 16);
+            self.compressed_data = &self.compressed_data[4..];
+            if self.compressed_data.len() < length {
+                return Err(Error::UnexpectedEOF);
+            }
+            let (chunk, rest) = self.compressed_data.split_at(length);
+            self.compressed_data = rest;
+
+            match chunk_type {
+                0x00 | 0x01 => {
+                    if chunk.len() < 4 {
+                        return Err(Error::InvalidInputSnappy);
+                    }
+                    let expected_crc = unmask_crc32c(LittleEndian::read_u32(&chunk[..4]));
+                    let data = &chunk[4..];
+                    self.uncompressed_pos = 0;
+                    self.uncompressed_chunk.clear();
+                    if chunk_type == 0x00 {
+                        try!(uncompress_into(data, &mut self.uncompressed_chunk));
+                    } else {
+                        self.uncompressed_chunk.extend_from_slice(data);
+                    }
+                    if crc32c(&self.uncompressed_chunk) != expected_crc {
+                        return Err(Error::InvalidInputSnappy);
+                    }
+                    return Ok(true);
+                }
+                0x02...0x7f => return Err(Error::InvalidInputSnappy),
+                // 0x80..0xfd: skippable; 0xfe: padding; 0xff: (another)
+                // stream identifier chunk -- none of these carry data
+                _ => continue,
+            }
+        }
+    }
+
     fn _read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
         let init_len = buf.len();
         // ~ first consume already uncompressed and unconsumed data - if any
@@ -131,20 +226,49 @@ impl<'a> SnappyReader<'a> {
             buf.extend_from_slice(rest);
             self.uncompressed_pos += rest.len();
         }
-        // ~ now decompress data directly to the output target
-        while !self.compressed_data.is_empty() {
-            let chunk_size = next_i32!(self.compressed_data);
-            if chunk_size <= 0 {
-                return Err(Error::InvalidInputSnappy);
-            }
-            let (c1, c2) = self.compressed_data.split_at(chunk_size as usize);
-            try!(uncompress_into(c1, buf));
-            self.compressed_data = c2;
+        // ~ now decompress chunk by chunk directly to the output target
+        while try!(self.next_chunk()) {
+            buf.extend_from_slice(&self.uncompressed_chunk);
+            self.uncompressed_pos = self.uncompressed_chunk.len();
         }
         Ok(buf.len() - init_len)
     }
 }
 
+// ~ reverses the masking snappy applies to the CRC32C it stores
+// alongside each chunk of the official frame format
+fn unmask_crc32c(masked: u32) -> u32 {
+    let rot = masked.wrapping_sub(0xa282ead8);
+    (rot >> 17) | (rot << 15)
+}
+
+// --------------------------------------------------------------------
+
+use super::{Compressor, Decompressor};
+
+/// The snappy codec plugged into the generic compression subsystem;
+/// wraps the xerial-chunked `compress`/`SnappyReader` pair above.
+pub struct Snappy;
+
+impl Compressor for Snappy {
+    fn compress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        {
+            let mut w = SnappyWriter::new(&mut out);
+            try!(w.write_all(src).map_err(Error::Io));
+            try!(w.flush().map_err(Error::Io));
+        }
+        Ok(out)
+    }
+}
+
+impl Decompressor for Snappy {
+    fn reader<'a>(&self, src: &'a [u8]) -> Result<Box<Read + 'a>> {
+        let r = try!(SnappyReader::new(src));
+        Ok(Box::new(r))
+    }
+}
+
 macro_rules! to_io_error {
     ($expr:expr) => {
         match $expr {
@@ -169,14 +293,175 @@ impl<'a> Read for SnappyReader<'a> {
 
 // --------------------------------------------------------------------
 
+// ~ reads exactly `buf.len()` bytes from `reader`, same as
+// `Read::read_exact`, except a clean EOF *before any byte* is read
+// yields `Ok(false)` instead of an error -- callers use this to tell
+// "no more frames" apart from a truncated one.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) if total == 0 => return Ok(false),
+            Ok(0) => return Err(Error::UnexpectedEOF),
+            Ok(n) => total += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(From::from(e)),
+        }
+    }
+    Ok(true)
+}
+
+// ~ a single xerial chunk claiming to be bigger than this is corrupt
+// input, not something worth an up-front multi-gigabyte allocation
+// attempt to find out
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reads a single frame of the xerial chunked snappy format -- a
+/// big-endian `i32` compressed-length prefix followed by exactly that
+/// many compressed bytes -- from a `BufRead`, decoding strictly up to
+/// the declared length and consuming no further. This lets a single
+/// underlying reader carry a snappy message set followed by other
+/// protocol data: once this returns, `reader`'s cursor sits exactly at
+/// the frame boundary. Returns `Ok(None)` once `reader` is exhausted.
+pub fn read_frame<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut len_buf = [0u8; 4];
+    if !try!(read_exact_or_eof(reader, &mut len_buf)) {
+        return Ok(None);
+    }
+    let chunk_size = BigEndian::read_i32(&len_buf);
+    if chunk_size < 0 || chunk_size as usize > MAX_FRAME_SIZE {
+        return Err(Error::InvalidInputSnappy);
+    }
+    let mut compressed = vec![0u8; chunk_size as usize];
+    try!(reader.read_exact(&mut compressed).or_else(|e| Err(From::from(e))));
+    if compressed.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+    let mut uncompressed = Vec::new();
+    try!(uncompress_into(&compressed, &mut uncompressed));
+    Ok(Some(uncompressed))
+}
+
+// --------------------------------------------------------------------
+
+// ~ the default chunk size snappy-java's SnappyOutputStream uses
+const DEFAULT_CHUNK_SIZE: usize = 32 * 1024;
+
+/// A streaming writer producing the xerial chunked snappy stream
+/// format that `SnappyReader` reads: a magic/version/compat header
+/// followed by, per chunk, a big-endian `i32` compressed-length prefix
+/// and the rsnappy-compressed block.
+pub struct SnappyWriter<W> {
+    inner: W,
+    chunk_size: usize,
+    header_written: bool,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> SnappyWriter<W> {
+    pub fn new(inner: W) -> SnappyWriter<W> {
+        SnappyWriter::with_chunk_size(inner, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(inner: W, chunk_size: usize) -> SnappyWriter<W> {
+        SnappyWriter {
+            inner: inner,
+            chunk_size: chunk_size,
+            header_written: false,
+            buf: Vec::with_capacity(chunk_size),
+        }
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            try!(self.inner.write_all(MAGIC));
+            try!(self.inner.write_i32::<BigEndian>(1)); // version
+            try!(self.inner.write_i32::<BigEndian>(1)); // compat
+            self.header_written = true;
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, data: &[u8]) -> io::Result<()> {
+        let compressed = try!(to_io_error!(compress(data)));
+        try!(self.inner.write_i32::<BigEndian>(compressed.len() as i32));
+        self.inner.write_all(&compressed)
+    }
+
+    fn flush_full_chunks(&mut self) -> io::Result<()> {
+        while self.buf.len() >= self.chunk_size {
+            let chunk = self.buf.drain(..self.chunk_size).collect::<Vec<u8>>();
+            try!(self.write_chunk(&chunk));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SnappyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        try!(self.write_header());
+        self.buf.extend_from_slice(buf);
+        try!(self.flush_full_chunks());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.write_header());
+        if !self.buf.is_empty() {
+            let chunk = mem::replace(&mut self.buf, Vec::new());
+            try!(self.write_chunk(&chunk));
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for SnappyWriter<W> {
+    fn drop(&mut self) {
+        // ~ best-effort: a dropped writer can't report flush errors
+        let _ = self.flush();
+    }
+}
+
+// --------------------------------------------------------------------
+
 #[cfg(test)]
 mod tests {
     extern crate rsnappy;
 
-    use std::io::Read;
+    use std::io::{Cursor, Read, Write};
+    use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
 
+    use crc::crc32c;
     use error::{Error, Result};
-    use super::{compress, uncompress_into, SnappyReader};
+    use super::{compress, read_frame, uncompress_into, SnappyReader, SnappyWriter};
+
+    // ~ the inverse of `super::unmask_crc32c`, used here to build
+    // official-format test fixtures
+    fn mask_crc32c(crc: u32) -> u32 {
+        let rot = (crc << 17) | (crc >> 15);
+        rot.wrapping_add(0xa282ead8)
+    }
+
+    fn official_frame(chunk_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.push(chunk_type);
+        let len = payload.len();
+        frame.push((len & 0xff) as u8);
+        frame.push(((len >> 8) & 0xff) as u8);
+        frame.push(((len >> 16) & 0xff) as u8);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn official_stream(msg: &[u8]) -> Vec<u8> {
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&official_frame(0xff, b"sNaPpY"));
+        let mut payload = vec![0u8; 4];
+        LittleEndian::write_u32(&mut payload, mask_crc32c(crc32c(msg)));
+        payload.extend_from_slice(&compress(msg).unwrap());
+        stream.extend_from_slice(&official_frame(0x00, &payload));
+        stream
+    }
 
     fn uncompress(src: &[u8]) -> Result<Vec<u8>> {
         let mut v = Vec::new();
@@ -240,6 +525,33 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_snappy_reader_official_format() {
+        let msg = b"Hello, official snappy frame format!";
+        let stream = official_stream(msg);
+
+        let mut r = SnappyReader::new(&stream).unwrap();
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf).unwrap();
+        assert_eq!(&buf[..], &msg[..]);
+    }
+
+    #[test]
+    fn test_snappy_reader_official_format_crc_mismatch() {
+        let msg = b"Hello, official snappy frame format!";
+        let mut stream = official_stream(msg);
+        // ~ corrupt a byte of the masked crc so it no longer matches
+        let crc_byte_offset = stream.len() - compress(msg).unwrap().len() - 4;
+        stream[crc_byte_offset] ^= 0xff;
+
+        let err: Result<()> = SnappyReader::new(&stream)
+            .and_then(|mut r| {
+                let mut buf = Vec::new();
+                r._read_to_end(&mut buf).map(|_| ())
+            });
+        assert!(if let Err(Error::InvalidInputSnappy) = err { true } else { false });
+    }
+
     #[test]
     fn test_snappy_reader_read_to_end_multi() {
         for _ in 0 .. 3 {
@@ -250,6 +562,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_snappy_writer_roundtrip() {
+        let mut out = Vec::new();
+        {
+            let mut w = SnappyWriter::with_chunk_size(&mut out, 8);
+            w.write_all(ORIGINAL.as_bytes()).unwrap();
+            w.flush().unwrap();
+        }
+
+        let mut buf = Vec::new();
+        let mut r = SnappyReader::new(&out).unwrap();
+        r.read_to_end(&mut buf).unwrap();
+        assert_eq!(ORIGINAL.as_bytes(), &buf[..]);
+    }
+
+    #[test]
+    fn test_read_frame_stops_exactly_at_boundary() {
+        let compressed = compress(b"hello").unwrap();
+        let mut data = Vec::new();
+        data.write_i32::<BigEndian>(compressed.len() as i32).unwrap();
+        data.extend_from_slice(&compressed);
+        data.extend_from_slice(b"trailing");
+
+        let mut cursor = Cursor::new(&data[..]);
+        let frame = read_frame(&mut cursor).unwrap();
+        assert_eq!(frame, Some(b"hello".to_vec()));
+        assert_eq!(cursor.position(), (data.len() - b"trailing".len()) as u64);
+
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"trailing");
+    }
+
+    #[test]
+    fn test_read_frame_zero_length_consumes_header_without_stalling() {
+        let mut data = Vec::new();
+        data.write_i32::<BigEndian>(0).unwrap();
+        data.extend_from_slice(b"trailing");
+
+        let mut cursor = Cursor::new(&data[..]);
+        let frame = read_frame(&mut cursor).unwrap();
+        assert_eq!(frame, Some(Vec::new()));
+        assert_eq!(cursor.position(), 4);
+
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"trailing");
+    }
+
+    #[test]
+    fn test_read_frame_rejects_oversized_declared_length() {
+        // ~ a header claiming a chunk far larger than MAX_FRAME_SIZE
+        // must be rejected without attempting to allocate/read it,
+        // even though none of those bytes actually follow
+        let mut data = Vec::new();
+        data.write_i32::<BigEndian>(i32::max_value()).unwrap();
+
+        let mut cursor = Cursor::new(&data[..]);
+        let err = read_frame(&mut cursor).unwrap_err();
+        assert!(if let Error::InvalidInputSnappy = err { true } else { false });
+    }
+
+    #[test]
+    fn test_read_frame_eof() {
+        let data: Vec<u8> = Vec::new();
+        let mut cursor = Cursor::new(&data[..]);
+        assert_eq!(read_frame(&mut cursor).unwrap(), None);
+    }
+
     #[cfg(feature = "nightly")]
     mod benches {
         extern crate rsnappy;
@@ -0,0 +1,45 @@
+extern crate zstd;
+
+use std::io::{Read, Write};
+
+use error::{Error, Result};
+use super::{Compressor, Decompressor};
+
+/// Compresses/decompresses message-set payloads using zstd, as
+/// negotiated by recent Kafka clients for its better compression ratio.
+pub struct Zstd;
+
+impl Compressor for Zstd {
+    fn compress(&self, src: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = try!(zstd::stream::Encoder::new(Vec::new(), 0).map_err(Error::Io));
+        try!(encoder.write_all(src).map_err(Error::Io));
+        encoder.finish().map_err(Error::Io)
+    }
+}
+
+impl Decompressor for Zstd {
+    fn reader<'a>(&self, src: &'a [u8]) -> Result<Box<Read + 'a>> {
+        let decoder = try!(zstd::stream::Decoder::new(src).map_err(Error::Io));
+        Ok(Box::new(decoder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compressor, Decompressor, Zstd};
+
+    #[test]
+    fn test_roundtrip() {
+        let msg = b"This is test data for the zstd codec";
+        let compressed = Zstd.compress(msg).unwrap();
+        let decompressed = Zstd.decompress(&compressed).unwrap();
+        assert_eq!(&decompressed[..], &msg[..]);
+    }
+
+    #[test]
+    fn test_decompress_corrupt_input() {
+        // ~ not a valid zstd frame at all
+        let garbage = [0u8, 1, 2, 3, 4, 5, 6, 7];
+        assert!(Zstd.decompress(&garbage).is_err());
+    }
+}